@@ -19,6 +19,7 @@ extern crate test;
 extern crate wyrm;
 
 use ndarray::Axis;
+use ndarray::Array1;
 
 use rayon::prelude::*;
 use rand::distributions::{IndependentSample, Range};
@@ -30,6 +31,11 @@ use wyrm::{Arr, DataInput};
 pub type UserId = usize;
 pub type ItemId = usize;
 
+/// A user's interaction history, in timestamp order, oldest first. Used by
+/// `RecurrentRecommenderModel`, which needs to know the order items were
+/// interacted with rather than just which items were interacted with.
+pub type UserSequence = Vec<ItemId>;
+
 pub struct InteractionMatrix {
     num_users: usize,
     num_items: usize,
@@ -149,6 +155,84 @@ impl<'a> Interaction for UnweightedInteraction {
     }
 }
 
+#[derive(Clone, Serialize, Deserialize, Debug)]
+pub struct WeightedInteraction {
+    user_id: UserId,
+    item_id: ItemId,
+    weight: f32,
+}
+
+impl WeightedInteraction {
+    pub fn new(user_id: UserId, item_id: ItemId, weight: f32) -> Self {
+        WeightedInteraction {
+            user_id,
+            item_id,
+            weight,
+        }
+    }
+}
+
+impl Interaction for WeightedInteraction {
+    fn user_id(&self) -> UserId {
+        self.user_id
+    }
+    fn item_id(&self) -> ItemId {
+        self.item_id
+    }
+    fn weight(&self) -> f32 {
+        self.weight
+    }
+}
+
+/// A single observed (or sampled) interaction described by a sparse feature
+/// vector, rather than a scalar user/item id pair. Used by
+/// `FactorizationMachineModel`, which needs to be able to score arbitrary
+/// combinations of user and item metadata instead of only raw ids.
+pub trait FeatureInteraction: Sync + Clone {
+    /// Indices of the nonzero entries of the feature vector.
+    fn indices(&self) -> &[usize];
+    /// Values of the nonzero entries, aligned with `indices`.
+    fn values(&self) -> &[f32];
+    fn weight(&self) -> f32;
+}
+
+#[derive(Clone, Serialize, Deserialize, Debug)]
+pub struct SparseInteraction {
+    indices: Vec<usize>,
+    values: Vec<f32>,
+}
+
+impl SparseInteraction {
+    pub fn new(indices: Vec<usize>, values: Vec<f32>) -> Self {
+        assert_eq!(
+            indices.len(),
+            values.len(),
+            "indices and values must be the same length"
+        );
+
+        SparseInteraction { indices, values }
+    }
+}
+
+impl FeatureInteraction for SparseInteraction {
+    fn indices(&self) -> &[usize] {
+        &self.indices
+    }
+    fn values(&self) -> &[f32] {
+        &self.values
+    }
+    fn weight(&self) -> f32 {
+        1.0
+    }
+}
+
+fn get_num_features<T: FeatureInteraction>(data: &[T]) -> usize {
+    data.iter()
+        .flat_map(|x| x.indices().iter().cloned())
+        .max()
+        .unwrap() + 1
+}
+
 pub fn train_test_split<T: Interaction, R: Rng>(
     interactions: &[T],
     rng: &mut R,
@@ -166,11 +250,140 @@ fn embedding_init(rows: usize, cols: usize) -> wyrm::Arr {
     Arr::zeros((rows, cols)).map(|_| rand::random::<f32>() / (cols as f32).sqrt())
 }
 
+/// The update rule used to apply gradients to the shared parameters of a
+/// Hogwild partition. `Sgd` is a reasonable default, but both `Momentum`
+/// and `Adagrad` tend to converge faster on the sparse, heavy-tailed
+/// gradients typical of implicit feedback: `Adagrad` in particular gives
+/// rarely-seen items an effectively larger learning rate.
+#[derive(Clone, Copy, Debug)]
+pub enum Optimizer {
+    Sgd,
+    /// `v <- decay * v + grad`, `theta <- theta - lr * v`.
+    Momentum { decay: f32 },
+    /// `g_sum <- g_sum + grad^2`, `theta <- theta - lr * grad / (sqrt(g_sum) + eps)`.
+    Adagrad,
+}
+
+impl std::default::Default for Optimizer {
+    fn default() -> Self {
+        Optimizer::Sgd
+    }
+}
+
+/// The training objective used by `fit`. Both variants are weighted by
+/// each example's `Interaction::weight()`, so confidence, play counts, or
+/// dwell time can down- or up-weight individual examples.
+#[derive(Clone, Copy, Debug)]
+pub enum Loss {
+    /// Pairwise BPR: contrasts an observed `(user, positive)` pair against
+    /// a sampled-negative item.
+    Bpr,
+    /// Pointwise logistic regression: `(user, positive)` pairs are labeled
+    /// `1` and sampled-negative pairs are labeled `0`, and both are scored
+    /// under `-[y·log σ(s) + (1-y)·log(1-σ(s))]`.
+    PointwiseLogistic,
+}
+
+impl std::default::Default for Loss {
+    fn default() -> Self {
+        Loss::Bpr
+    }
+}
+
+enum OptimizerInstance {
+    Sgd(wyrm::SGD),
+    Momentum(wyrm::Momentum),
+    Adagrad(wyrm::Adagrad),
+}
+
+impl OptimizerInstance {
+    fn step(&mut self) {
+        match *self {
+            OptimizerInstance::Sgd(ref mut optimizer) => optimizer.step(),
+            OptimizerInstance::Momentum(ref mut optimizer) => optimizer.step(),
+            OptimizerInstance::Adagrad(ref mut optimizer) => optimizer.step(),
+        }
+    }
+}
+
+/// Per-parameter accumulator buffers for optimizers that need more state
+/// than the gradient itself: `Momentum`'s velocity and `Adagrad`'s running
+/// sum of squared gradients. These are `HogwildParameter`s built once in
+/// `build_model`, alongside the parameters they track, and handed to every
+/// Hogwild partition's optimizer by reference (`Arc::clone`) rather than
+/// being owned by the per-partition `OptimizerInstance` itself - that is
+/// what makes them persist across minibatches/epochs and be genuinely
+/// shared across partitions, rather than each partition silently keeping
+/// its own private, unsynchronized copy.
+///
+/// Only the accumulator the selected `Optimizer` actually uses is
+/// allocated; the other field stays empty, so `Sgd` (and the unused half
+/// of `Momentum`/`Adagrad`) doesn't carry a parameter-sized buffer it
+/// never reads.
+struct OptimizerAccumulators {
+    velocity: Vec<Arc<wyrm::HogwildParameter>>,
+    squared_gradient: Vec<Arc<wyrm::HogwildParameter>>,
+}
+
+impl OptimizerAccumulators {
+    fn for_params(optimizer: Optimizer, params: &[&Arc<wyrm::HogwildParameter>]) -> Self {
+        let zeros_like = |param: &&Arc<wyrm::HogwildParameter>| {
+            Arc::new(wyrm::HogwildParameter::new(Arr::zeros(param.value.dim())))
+        };
+
+        OptimizerAccumulators {
+            velocity: match optimizer {
+                Optimizer::Momentum { .. } => params.iter().map(&zeros_like).collect(),
+                Optimizer::Sgd | Optimizer::Adagrad => Vec::new(),
+            },
+            squared_gradient: match optimizer {
+                Optimizer::Adagrad => params.iter().map(&zeros_like).collect(),
+                Optimizer::Sgd | Optimizer::Momentum { .. } => Vec::new(),
+            },
+        }
+    }
+}
+
+/// Build the optimizer selected by `Hyperparameters::optimizer`/
+/// `FMHyperparameters::optimizer`/`RecurrentHyperparameters::optimizer`.
+/// `accumulators` must have been built by `OptimizerAccumulators::for_params`
+/// for the same optimizer over the same parameters, in the same order as
+/// `params`, and must be the same `OptimizerAccumulators` instance used
+/// by every Hogwild partition training this model - see its doc comment.
+fn build_optimizer(
+    optimizer: Optimizer,
+    learning_rate: f32,
+    params: Vec<wyrm::ParameterNode>,
+    accumulators: &OptimizerAccumulators,
+) -> OptimizerInstance {
+    match optimizer {
+        Optimizer::Sgd => OptimizerInstance::Sgd(wyrm::SGD::new(learning_rate, params)),
+        Optimizer::Momentum { decay } => OptimizerInstance::Momentum(wyrm::Momentum::new(
+            learning_rate,
+            decay,
+            params,
+            accumulators.velocity.clone(),
+        )),
+        Optimizer::Adagrad => OptimizerInstance::Adagrad(wyrm::Adagrad::new(
+            learning_rate,
+            params,
+            accumulators.squared_gradient.clone(),
+        )),
+    }
+}
+
 #[derive(Builder)]
 pub struct Hyperparameters {
     #[builder(default = "16")] latent_dim: usize,
     #[builder(default = "10")] minibatch_size: usize,
     #[builder(default = "0.01")] learning_rate: f32,
+    #[builder(default = "Optimizer::Sgd")] optimizer: Optimizer,
+    #[builder(default = "Loss::Bpr")] loss: Loss,
+    /// Seeds the per-epoch minibatch reshuffling, so that training runs
+    /// with the same seed see the interactions in the same order. The
+    /// sampled negative items are drawn from an unseeded generator and
+    /// differ from run to run.
+    #[builder(default = "[1, 2, 3, 4]")] seed: [u32; 4],
 }
 
 struct ModelData {
@@ -179,6 +392,7 @@ struct ModelData {
     user_embedding: Arc<wyrm::HogwildParameter>,
     item_embedding: Arc<wyrm::HogwildParameter>,
     item_biases: Arc<wyrm::HogwildParameter>,
+    optimizer_state: OptimizerAccumulators,
 }
 
 pub struct ImplicitFactorizationModel {
@@ -255,12 +469,19 @@ impl ImplicitFactorizationModel {
 
         let item_biases = Arc::new(wyrm::HogwildParameter::new(embedding_init(num_items, 1)));
 
+        let optimizer_state =
+            OptimizerAccumulators::for_params(
+                self.hyper.optimizer,
+                &[&user_embeddings, &item_embeddings, &item_biases],
+            );
+
         ModelData {
             num_users: num_users,
             num_items: num_items,
             user_embedding: user_embeddings,
             item_embedding: item_embeddings,
             item_biases: item_biases,
+            optimizer_state: optimizer_state,
         }
     }
 
@@ -309,48 +530,434 @@ impl ImplicitFactorizationModel {
                 let negative_prediciton =
                     user_vector.vector_dot(&negative_item_vector) + negative_item_bias;
 
-                let score_diff = positive_prediction - negative_prediciton;
-                let mut loss = -score_diff.sigmoid();
+                let weight_idx = wyrm::InputNode::new(Arr::zeros((minibatch_size, 1)));
 
-                let mut optimizer = wyrm::SGD::new(
+                let mut optimizer = build_optimizer(
+                    self.hyper.optimizer,
                     self.hyper.learning_rate,
                     vec![
                         user_embeddings.clone(),
                         item_embeddings.clone(),
                         item_biases.clone(),
                     ],
+                    &self.model.as_ref().unwrap().optimizer_state,
                 );
 
                 let mut batch_uids = vec![0; minibatch_size];
                 let mut batch_positives = vec![0; minibatch_size];
                 let mut batch_negatives = vec![0; minibatch_size];
+                let mut batch_weights = vec![0.0; minibatch_size];
 
                 let mut rng = rand::XorShiftRng::from_seed(thread_rng().gen());
                 let start = partition_idx * chunk_size;
-                let stop = start + chunk_size;
-
-                let mut loss_value = 0.0;
-
-                for _ in 0..num_epochs {
-                    for interaction in interactions[start..stop].chunks(minibatch_size) {
-                        if interaction.len() < minibatch_size {
-                            break;
-                        }
-
-                        for (uid, p_iid, n_iid, datum) in izip!(
+                let stop = if partition_idx + 1 == num_partitions {
+                    interactions.len()
+                } else {
+                    start + chunk_size
+                };
+                let chunk_len = stop - start;
+
+                // One global permutation of every interaction index,
+                // reshuffled fresh at the start of each epoch. Every
+                // partition derives it from the same seed and reshuffles in
+                // the same order, so all partitions see an identical
+                // permutation each epoch without any cross-thread
+                // synchronization; each partition then trains on its own
+                // fixed `[start, stop)` slice of it, so the set of
+                // interactions a partition sees changes from epoch to
+                // epoch instead of being frozen to the same
+                // ~1/num_partitions subset for the whole run. The buffer
+                // itself is allocated once and reshuffled in place, so
+                // memory stays O(N) per partition rather than growing with
+                // `num_epochs`.
+                let mut permutation: Vec<usize> = (0..interactions.len()).collect();
+                let mut shuffle_rng = rand::XorShiftRng::from_seed(self.hyper.seed);
+
+                // Round up so the last, possibly-partial minibatch is
+                // filled by wrapping around to the start of this
+                // partition's share of the permutation rather than being
+                // dropped.
+                let num_minibatches = (chunk_len + minibatch_size - 1) / minibatch_size;
+
+                macro_rules! fill_batch {
+                    ($minibatch:expr) => {
+                        for (uid, p_iid, n_iid, w, slot) in izip!(
                             batch_uids.iter_mut(),
                             batch_positives.iter_mut(),
                             batch_negatives.iter_mut(),
-                            interaction
+                            batch_weights.iter_mut(),
+                            0..minibatch_size
                         ) {
+                            let datum = &interactions[permutation
+                                [start + ($minibatch * minibatch_size + slot) % chunk_len]];
+
                             *uid = datum.user_id();
                             *p_iid = datum.item_id();
                             *n_iid = negative_item_range.ind_sample(&mut rng);
+                            *w = datum.weight();
                         }
 
                         user_idx.set_value(batch_uids.as_slice());
                         positive_item_idx.set_value(batch_positives.as_slice());
                         negative_item_idx.set_value(batch_negatives.as_slice());
+                        weight_idx.set_value(batch_weights.as_slice());
+                    };
+                }
+
+                let mut loss_value = 0.0;
+
+                match self.hyper.loss {
+                    Loss::Bpr => {
+                        let score_diff = positive_prediction - negative_prediciton;
+                        let mut loss = -score_diff.sigmoid() * weight_idx;
+
+                        for _ in 0..num_epochs {
+                            shuffle_rng.shuffle(&mut permutation);
+
+                            for minibatch in 0..num_minibatches {
+                                fill_batch!(minibatch);
+
+                                loss.forward();
+                                loss.backward(1.0);
+
+                                loss_value += loss.value().scalar_sum();
+
+                                optimizer.step();
+                                loss.zero_gradient();
+                            }
+                        }
+                    }
+                    Loss::PointwiseLogistic => {
+                        // (user, positive) pairs are labeled 1, sampled
+                        // (user, negative) pairs are labeled 0; cross
+                        // entropy against those labels collapses to the
+                        // sum of the two log-sigmoid terms below.
+                        let mut loss = -(positive_prediction.sigmoid().ln()
+                            + (-negative_prediciton).sigmoid().ln())
+                            * weight_idx;
+
+                        for _ in 0..num_epochs {
+                            shuffle_rng.shuffle(&mut permutation);
+
+                            for minibatch in 0..num_minibatches {
+                                fill_batch!(minibatch);
+
+                                loss.forward();
+                                loss.backward(1.0);
+
+                                loss_value += loss.value().scalar_sum();
+
+                                optimizer.step();
+                                loss.zero_gradient();
+                            }
+                        }
+                    }
+                }
+
+                loss_value / (num_epochs * num_minibatches * minibatch_size) as f32
+            })
+            .collect();
+
+        Ok(losses.into_iter().sum())
+    }
+}
+
+#[derive(Builder)]
+pub struct FMHyperparameters {
+    #[builder(default = "16")] latent_dim: usize,
+    #[builder(default = "10")] minibatch_size: usize,
+    #[builder(default = "0.01")] learning_rate: f32,
+    #[builder(default = "Optimizer::Sgd")] optimizer: Optimizer,
+    /// Hard cap on the number of nonzero (index, value) entries `fit` will
+    /// look at for each example. Rows with fewer nonzeros than this should
+    /// be padded with index `0`, value `0.0`, which contributes nothing to
+    /// the prediction; rows with *more* than this are rejected by `fit`
+    /// rather than silently truncated, since training on a prefix of the
+    /// feature vector while `predict` scores the whole thing would be a
+    /// silent train/serve skew.
+    #[builder(default = "8")] num_features_per_example: usize,
+}
+
+struct FactorizationMachineData {
+    num_features: usize,
+    feature_embedding: Arc<wyrm::HogwildParameter>,
+    feature_weights: Arc<wyrm::HogwildParameter>,
+    bias: Arc<wyrm::HogwildParameter>,
+    optimizer_state: OptimizerAccumulators,
+}
+
+/// A factorization machine over arbitrary sparse feature vectors, able to
+/// score user/item metadata (genres, demographics, and the like) in
+/// addition to the raw ids handled by `ImplicitFactorizationModel`. The
+/// prediction for a feature vector `x` is the standard FM form
+///
+/// `y(x) = w0 + Σ_i w_i x_i + Σ_{i<j} <v_i, v_j> x_i x_j`
+///
+/// with the second-order term evaluated in `O(nnz · latent_dim)` via the
+/// identity
+///
+/// `Σ_{i<j} <v_i, v_j> x_i x_j = 0.5 · Σ_f [ (Σ_i v_{i,f} x_i)² − Σ_i v_{i,f}² x_i² ]`
+pub struct FactorizationMachineModel {
+    hyper: FMHyperparameters,
+    model: Option<FactorizationMachineData>,
+}
+
+impl std::default::Default for FactorizationMachineModel {
+    fn default() -> Self {
+        FactorizationMachineModel {
+            hyper: FMHyperparametersBuilder::default().build().unwrap(),
+            model: None,
+        }
+    }
+}
+
+impl FactorizationMachineModel {
+    pub fn new(hyper: FMHyperparameters) -> Self {
+        FactorizationMachineModel {
+            hyper: hyper,
+            model: None,
+        }
+    }
+
+    pub fn num_features(&self) -> Option<usize> {
+        match &self.model {
+            &Some(ref model) => Some(model.num_features),
+            _ => None,
+        }
+    }
+
+    /// Score an arbitrary feature vector. Because the model only ever looks
+    /// up the embeddings and weights named by `indices`, this works just as
+    /// well for cold-start users or items as for ones seen during training,
+    /// as long as their features were observed somewhere in the training
+    /// data.
+    pub fn predict(&self, indices: &[usize], values: &[f32]) -> Result<f32, &'static str> {
+        let model = match self.model {
+            Some(ref model) => model,
+            None => return Err("Model must be fitted first."),
+        };
+
+        let latent_dim = self.hyper.latent_dim;
+        let embeddings = &model.feature_embedding.value;
+        let weights = model.feature_weights.value.as_slice().unwrap();
+        let bias = model.bias.value.as_slice().unwrap()[0];
+
+        let mut linear = bias;
+        let mut sum_vector = vec![0.0; latent_dim];
+        let mut sum_of_squares = 0.0;
+
+        for (&idx, &value) in indices.iter().zip(values) {
+            linear += weights[idx] * value;
+
+            let row = embeddings.subview(Axis(0), idx);
+            let row = row.as_slice().unwrap();
+
+            for (f, &v) in row.iter().enumerate() {
+                sum_vector[f] += v * value;
+            }
+            sum_of_squares += wyrm::simd_dot(row, row) * value * value;
+        }
+
+        let sum_of_squares_of_sum: f32 = sum_vector.iter().map(|&x| x * x).sum();
+        let second_order = 0.5 * (sum_of_squares_of_sum - sum_of_squares);
+
+        Ok(linear + second_order)
+    }
+
+    fn build_model(&self, num_features: usize, latent_dim: usize) -> FactorizationMachineData {
+        let feature_embedding = Arc::new(wyrm::HogwildParameter::new(embedding_init(
+            num_features,
+            latent_dim,
+        )));
+        let feature_weights = Arc::new(wyrm::HogwildParameter::new(embedding_init(
+            num_features,
+            1,
+        )));
+        let bias = Arc::new(wyrm::HogwildParameter::new(Arr::zeros((1, 1))));
+
+        let optimizer_state =
+            OptimizerAccumulators::for_params(
+                self.hyper.optimizer,
+                &[&feature_embedding, &feature_weights, &bias],
+            );
+
+        FactorizationMachineData {
+            num_features: num_features,
+            feature_embedding: feature_embedding,
+            feature_weights: feature_weights,
+            bias: bias,
+            optimizer_state: optimizer_state,
+        }
+    }
+
+    pub fn fit<T: FeatureInteraction>(
+        &mut self,
+        interactions: &[T],
+        num_epochs: usize,
+    ) -> Result<f32, &'static str> {
+        let num_features = get_num_features(interactions);
+        let minibatch_size = self.hyper.minibatch_size;
+        let num_slots = self.hyper.num_features_per_example;
+
+        if interactions
+            .iter()
+            .any(|datum| datum.indices().len() > num_slots)
+        {
+            return Err(
+                "An interaction has more nonzero features than \
+                 FMHyperparameters::num_features_per_example; raise the cap \
+                 or split the feature vector.",
+            );
+        }
+
+        if self.model.is_none() {
+            self.model = Some(self.build_model(num_features, self.hyper.latent_dim));
+        }
+
+        let negative_sample_range = Range::new(0, interactions.len());
+
+        let num_partitions = rayon::current_num_threads();
+        let chunk_size = interactions.len() / num_partitions;
+
+        let losses: Vec<f32> = (0..rayon::current_num_threads())
+            .into_par_iter()
+            .map(|partition_idx| {
+                let feature_embeddings = wyrm::ParameterNode::shared(
+                    self.model.as_ref().unwrap().feature_embedding.clone(),
+                );
+                let feature_weights = wyrm::ParameterNode::shared(
+                    self.model.as_ref().unwrap().feature_weights.clone(),
+                );
+                let bias =
+                    wyrm::ParameterNode::shared(self.model.as_ref().unwrap().bias.clone());
+
+                let mut positive_idx = Vec::with_capacity(num_slots);
+                let mut positive_val = Vec::with_capacity(num_slots);
+                let mut negative_idx = Vec::with_capacity(num_slots);
+                let mut negative_val = Vec::with_capacity(num_slots);
+
+                let mut positive_score = bias.clone();
+                let mut negative_score = bias.clone();
+
+                let mut positive_sum = None;
+                let mut positive_square_sum = None;
+                let mut negative_sum = None;
+                let mut negative_square_sum = None;
+
+                for _ in 0..num_slots {
+                    let p_idx = wyrm::IndexInputNode::new(&vec![0; minibatch_size]);
+                    let p_val = wyrm::InputNode::new(Arr::zeros((minibatch_size, 1)));
+                    let n_idx = wyrm::IndexInputNode::new(&vec![0; minibatch_size]);
+                    let n_val = wyrm::InputNode::new(Arr::zeros((minibatch_size, 1)));
+
+                    let p_embedding = feature_embeddings.index(&p_idx) * p_val.clone();
+                    let n_embedding = feature_embeddings.index(&n_idx) * n_val.clone();
+
+                    positive_score = positive_score
+                        + feature_weights.index(&p_idx) * p_val.clone();
+                    negative_score = negative_score
+                        + feature_weights.index(&n_idx) * n_val.clone();
+
+                    let p_square = p_embedding.vector_dot(&p_embedding);
+                    let n_square = n_embedding.vector_dot(&n_embedding);
+
+                    positive_square_sum = Some(match positive_square_sum {
+                        None => p_square,
+                        Some(acc) => acc + p_square,
+                    });
+                    negative_square_sum = Some(match negative_square_sum {
+                        None => n_square,
+                        Some(acc) => acc + n_square,
+                    });
+
+                    positive_sum = Some(match positive_sum {
+                        None => p_embedding,
+                        Some(acc) => acc + p_embedding,
+                    });
+                    negative_sum = Some(match negative_sum {
+                        None => n_embedding,
+                        Some(acc) => acc + n_embedding,
+                    });
+
+                    positive_idx.push(p_idx);
+                    positive_val.push(p_val);
+                    negative_idx.push(n_idx);
+                    negative_val.push(n_val);
+                }
+
+                let positive_sum = positive_sum.unwrap();
+                let negative_sum = negative_sum.unwrap();
+
+                let positive_second_order = (positive_sum.vector_dot(&positive_sum)
+                    - positive_square_sum.unwrap())
+                    * 0.5;
+                let negative_second_order = (negative_sum.vector_dot(&negative_sum)
+                    - negative_square_sum.unwrap())
+                    * 0.5;
+
+                let positive_prediction = positive_score + positive_second_order;
+                let negative_prediction = negative_score + negative_second_order;
+
+                let score_diff = positive_prediction - negative_prediction;
+                let mut loss = -score_diff.sigmoid();
+
+                let mut optimizer = build_optimizer(
+                    self.hyper.optimizer,
+                    self.hyper.learning_rate,
+                    vec![
+                        feature_embeddings.clone(),
+                        feature_weights.clone(),
+                        bias.clone(),
+                    ],
+                    &self.model.as_ref().unwrap().optimizer_state,
+                );
+
+                let mut rng = rand::XorShiftRng::from_seed(thread_rng().gen());
+                let start = partition_idx * chunk_size;
+                let stop = start + chunk_size;
+
+                let mut loss_value = 0.0;
+
+                let mut batch_positive_idx = vec![vec![0; minibatch_size]; num_slots];
+                let mut batch_positive_val = vec![vec![0.0; minibatch_size]; num_slots];
+                let mut batch_negative_idx = vec![vec![0; minibatch_size]; num_slots];
+                let mut batch_negative_val = vec![vec![0.0; minibatch_size]; num_slots];
+
+                for _ in 0..num_epochs {
+                    for interaction in interactions[start..stop].chunks(minibatch_size) {
+                        if interaction.len() < minibatch_size {
+                            break;
+                        }
+
+                        for (row, datum) in interaction.iter().enumerate() {
+                            let negative =
+                                &interactions[negative_sample_range.ind_sample(&mut rng)];
+
+                            for slot in 0..num_slots {
+                                let (p_idx, p_val) = datum
+                                    .indices()
+                                    .get(slot)
+                                    .map(|&i| (i, datum.values()[slot]))
+                                    .unwrap_or((0, 0.0));
+                                let (n_idx, n_val) = negative
+                                    .indices()
+                                    .get(slot)
+                                    .map(|&i| (i, negative.values()[slot]))
+                                    .unwrap_or((0, 0.0));
+
+                                batch_positive_idx[slot][row] = p_idx;
+                                batch_positive_val[slot][row] = p_val;
+                                batch_negative_idx[slot][row] = n_idx;
+                                batch_negative_val[slot][row] = n_val;
+                            }
+                        }
+
+                        for slot in 0..num_slots {
+                            positive_idx[slot].set_value(batch_positive_idx[slot].as_slice());
+                            positive_val[slot].set_value(batch_positive_val[slot].as_slice());
+                            negative_idx[slot].set_value(batch_negative_idx[slot].as_slice());
+                            negative_val[slot].set_value(batch_negative_val[slot].as_slice());
+                        }
 
                         loss.forward();
                         loss.backward(1.0);
@@ -370,6 +977,329 @@ impl ImplicitFactorizationModel {
     }
 }
 
+fn get_num_items_in_sequences(data: &[UserSequence]) -> usize {
+    data.iter()
+        .flat_map(|sequence| sequence.iter().cloned())
+        .max()
+        .unwrap() + 1
+}
+
+/// Picks a fixed-length, contiguous window out of `sequence` for use as a
+/// training example. Sequences longer than `length` contribute a random
+/// contiguous slice (so later epochs eventually see the whole history);
+/// sequences shorter than `length` are wrapped around so the RNN is always
+/// unrolled over exactly `length` steps.
+fn sequence_window<R: Rng>(sequence: &[ItemId], length: usize, rng: &mut R) -> Vec<ItemId> {
+    if sequence.len() >= length {
+        let start = Range::new(0, sequence.len() - length + 1).ind_sample(rng);
+        sequence[start..start + length].to_vec()
+    } else {
+        (0..length).map(|i| sequence[i % sequence.len()]).collect()
+    }
+}
+
+#[derive(Builder)]
+pub struct RecurrentHyperparameters {
+    #[builder(default = "16")] latent_dim: usize,
+    #[builder(default = "10")] minibatch_size: usize,
+    #[builder(default = "0.01")] learning_rate: f32,
+    #[builder(default = "Optimizer::Sgd")] optimizer: Optimizer,
+    #[builder(default = "[1, 2, 3, 4]")] seed: [u32; 4],
+    /// Number of items each training example is unrolled over, giving one
+    /// fewer next-item transitions to score. Longer histories are cut down to a random
+    /// contiguous window of this length; shorter ones are wrapped around.
+    #[builder(default = "8")] max_sequence_length: usize,
+}
+
+struct RecurrentModelData {
+    num_items: usize,
+    item_embedding: Arc<wyrm::HogwildParameter>,
+    item_biases: Arc<wyrm::HogwildParameter>,
+    input_weights: Arc<wyrm::HogwildParameter>,
+    hidden_weights: Arc<wyrm::HogwildParameter>,
+    hidden_bias: Arc<wyrm::HogwildParameter>,
+    optimizer_state: OptimizerAccumulators,
+}
+
+/// A next-item recommender that represents a user by the hidden state of
+/// an RNN run over their ordered interaction history, rather than by a
+/// learned per-user embedding row, so it can score the next item for cold
+/// or rapidly-evolving users as long as their recent history is known.
+///
+/// The recurrence is `h_t = tanh(W_x · e(x_t) + W_h · h_{t-1} + b)`, where
+/// `e(x_t)` is the embedding of the t-th item in the history; a candidate
+/// item is scored as `h_T · e(item) + bias(item)`. Training predicts item
+/// `x_{t+1}` from the prefix up to `t` under the same sampled-negative
+/// BPR/sigmoid objective used by `ImplicitFactorizationModel::fit`.
+pub struct RecurrentRecommenderModel {
+    hyper: RecurrentHyperparameters,
+    model: Option<RecurrentModelData>,
+}
+
+impl std::default::Default for RecurrentRecommenderModel {
+    fn default() -> Self {
+        RecurrentRecommenderModel {
+            hyper: RecurrentHyperparametersBuilder::default().build().unwrap(),
+            model: None,
+        }
+    }
+}
+
+impl RecurrentRecommenderModel {
+    pub fn new(hyper: RecurrentHyperparameters) -> Self {
+        RecurrentRecommenderModel {
+            hyper: hyper,
+            model: None,
+        }
+    }
+
+    pub fn num_items(&self) -> Option<usize> {
+        match &self.model {
+            &Some(ref model) => Some(model.num_items),
+            _ => None,
+        }
+    }
+
+    /// Score every item given a user's recent item history, oldest first.
+    /// Because the user is represented purely by the RNN's hidden state
+    /// over that history rather than a learned embedding row, this works
+    /// for users who were never seen during training.
+    pub fn predict(&self, history: &[ItemId]) -> Result<Vec<f32>, &'static str> {
+        let model = match self.model {
+            Some(ref model) => model,
+            None => return Err("Model must be fitted first."),
+        };
+
+        let mut hidden = Array1::zeros(self.hyper.latent_dim);
+
+        for &item_id in history {
+            let x = model.item_embedding.value.subview(Axis(0), item_id);
+            let bias = model.hidden_bias.value.subview(Axis(0), 0);
+
+            hidden = (x.dot(&model.input_weights.value) + hidden.dot(&model.hidden_weights.value)
+                + bias)
+                .map(|v| v.tanh());
+        }
+
+        let item_biases = model.item_biases.value.as_slice().unwrap();
+        let hidden_slice = hidden.as_slice().unwrap();
+
+        let predictions: Vec<f32> = model
+            .item_embedding
+            .value
+            .genrows()
+            .into_iter()
+            .zip(item_biases)
+            .map(|(item_embedding, item_bias)| {
+                item_bias + wyrm::simd_dot(hidden_slice, item_embedding.as_slice().unwrap())
+            })
+            .collect();
+
+        Ok(predictions)
+    }
+
+    fn build_model(&self, num_items: usize, latent_dim: usize) -> RecurrentModelData {
+        let item_embedding = Arc::new(wyrm::HogwildParameter::new(embedding_init(
+            num_items,
+            latent_dim,
+        )));
+        let item_biases = Arc::new(wyrm::HogwildParameter::new(embedding_init(num_items, 1)));
+        let input_weights = Arc::new(wyrm::HogwildParameter::new(embedding_init(
+            latent_dim,
+            latent_dim,
+        )));
+        let hidden_weights = Arc::new(wyrm::HogwildParameter::new(embedding_init(
+            latent_dim,
+            latent_dim,
+        )));
+        let hidden_bias = Arc::new(wyrm::HogwildParameter::new(Arr::zeros((1, latent_dim))));
+
+        let optimizer_state = OptimizerAccumulators::for_params(self.hyper.optimizer, &[
+            &item_embedding,
+            &item_biases,
+            &input_weights,
+            &hidden_weights,
+            &hidden_bias,
+        ]);
+
+        RecurrentModelData {
+            num_items: num_items,
+            item_embedding: item_embedding,
+            item_biases: item_biases,
+            input_weights: input_weights,
+            hidden_weights: hidden_weights,
+            hidden_bias: hidden_bias,
+            optimizer_state: optimizer_state,
+        }
+    }
+
+    pub fn fit(
+        &mut self,
+        sequences: &[UserSequence],
+        num_epochs: usize,
+    ) -> Result<f32, &'static str> {
+        let num_items = get_num_items_in_sequences(sequences);
+        let minibatch_size = self.hyper.minibatch_size;
+        let sequence_length = self.hyper.max_sequence_length;
+
+        if self.model.is_none() {
+            self.model = Some(self.build_model(num_items, self.hyper.latent_dim));
+        }
+
+        let negative_item_range = Range::new(0, num_items);
+
+        let num_partitions = rayon::current_num_threads();
+        let chunk_size = sequences.len() / num_partitions;
+
+        let losses: Vec<f32> = (0..rayon::current_num_threads())
+            .into_par_iter()
+            .map(|partition_idx| {
+                let item_embeddings = wyrm::ParameterNode::shared(
+                    self.model.as_ref().unwrap().item_embedding.clone(),
+                );
+                let item_biases = wyrm::ParameterNode::shared(
+                    self.model.as_ref().unwrap().item_biases.clone(),
+                );
+                let input_weights = wyrm::ParameterNode::shared(
+                    self.model.as_ref().unwrap().input_weights.clone(),
+                );
+                let hidden_weights = wyrm::ParameterNode::shared(
+                    self.model.as_ref().unwrap().hidden_weights.clone(),
+                );
+                let hidden_bias = wyrm::ParameterNode::shared(
+                    self.model.as_ref().unwrap().hidden_bias.clone(),
+                );
+
+                let mut item_idx = Vec::with_capacity(sequence_length);
+                let mut positive_idx = Vec::with_capacity(sequence_length - 1);
+                let mut negative_idx = Vec::with_capacity(sequence_length - 1);
+
+                let mut hidden = None;
+                let mut loss = None;
+
+                for t in 0..sequence_length {
+                    let idx = wyrm::IndexInputNode::new(&vec![0; minibatch_size]);
+                    let x = item_embeddings.index(&idx);
+
+                    let new_hidden = match hidden {
+                        None => (x.dot(&input_weights) + hidden_bias.clone()).tanh(),
+                        Some(h) => {
+                            (x.dot(&input_weights) + h.dot(&hidden_weights) + hidden_bias.clone())
+                                .tanh()
+                        }
+                    };
+
+                    if t + 1 < sequence_length {
+                        let p_idx = wyrm::IndexInputNode::new(&vec![0; minibatch_size]);
+                        let n_idx = wyrm::IndexInputNode::new(&vec![0; minibatch_size]);
+
+                        let positive_score = new_hidden.clone().vector_dot(
+                            &item_embeddings.index(&p_idx),
+                        ) + item_biases.index(&p_idx);
+                        let negative_score = new_hidden.clone().vector_dot(
+                            &item_embeddings.index(&n_idx),
+                        ) + item_biases.index(&n_idx);
+
+                        let score_diff = positive_score - negative_score;
+                        let step_loss = -score_diff.sigmoid();
+
+                        loss = Some(match loss {
+                            None => step_loss,
+                            Some(acc) => acc + step_loss,
+                        });
+
+                        positive_idx.push(p_idx);
+                        negative_idx.push(n_idx);
+                    }
+
+                    item_idx.push(idx);
+                    hidden = Some(new_hidden);
+                }
+
+                let mut loss = loss.unwrap();
+
+                let mut optimizer = build_optimizer(
+                    self.hyper.optimizer,
+                    self.hyper.learning_rate,
+                    vec![
+                        item_embeddings.clone(),
+                        item_biases.clone(),
+                        input_weights.clone(),
+                        hidden_weights.clone(),
+                        hidden_bias.clone(),
+                    ],
+                    &self.model.as_ref().unwrap().optimizer_state,
+                );
+
+                let mut rng = rand::XorShiftRng::from_seed(thread_rng().gen());
+                let start = partition_idx * chunk_size;
+                let stop = if partition_idx + 1 == num_partitions {
+                    sequences.len()
+                } else {
+                    start + chunk_size
+                };
+                let chunk_len = stop - start;
+
+                // See the comment on the same shuffling in
+                // `ImplicitFactorizationModel::fit`, which this mirrors:
+                // every partition derives the same permutation from the
+                // same seed and reshuffles it in place once per epoch, so
+                // memory stays O(N) per partition rather than growing with
+                // `num_epochs`.
+                let mut permutation: Vec<usize> = (0..sequences.len()).collect();
+                let mut shuffle_rng = rand::XorShiftRng::from_seed(self.hyper.seed);
+
+                let num_minibatches = (chunk_len + minibatch_size - 1) / minibatch_size;
+
+                let mut batch_items = vec![vec![0; minibatch_size]; sequence_length];
+                let mut batch_negatives = vec![vec![0; minibatch_size]; sequence_length - 1];
+
+                let mut loss_value = 0.0;
+
+                for _ in 0..num_epochs {
+                    shuffle_rng.shuffle(&mut permutation);
+
+                    for minibatch in 0..num_minibatches {
+                        for slot in 0..minibatch_size {
+                            let sequence = &sequences[permutation
+                                [start + (minibatch * minibatch_size + slot) % chunk_len]];
+                            let window = sequence_window(sequence, sequence_length, &mut rng);
+
+                            for (t, &item) in window.iter().enumerate() {
+                                batch_items[t][slot] = item;
+                            }
+                            for t in 0..sequence_length - 1 {
+                                batch_negatives[t][slot] =
+                                    negative_item_range.ind_sample(&mut rng);
+                            }
+                        }
+
+                        for t in 0..sequence_length {
+                            item_idx[t].set_value(batch_items[t].as_slice());
+                        }
+                        for t in 0..sequence_length - 1 {
+                            positive_idx[t].set_value(batch_items[t + 1].as_slice());
+                            negative_idx[t].set_value(batch_negatives[t].as_slice());
+                        }
+
+                        loss.forward();
+                        loss.backward(1.0);
+
+                        loss_value += loss.value().scalar_sum();
+
+                        optimizer.step();
+                        loss.zero_gradient();
+                    }
+                }
+
+                loss_value / (num_epochs * num_minibatches * minibatch_size) as f32
+            })
+            .collect();
+
+        Ok(losses.into_iter().sum())
+    }
+}
+
 #[cfg(test)]
 mod tests {
 
@@ -418,6 +1348,126 @@ mod tests {
         assert!(mrr > 0.09);
     }
 
+    #[test]
+    fn it_works_with_adagrad() {
+        let data = load_movielens("data.csv");
+
+        let hyper = HyperparametersBuilder::default()
+            .learning_rate(0.1)
+            .latent_dim(32)
+            .optimizer(Optimizer::Adagrad)
+            .build()
+            .unwrap();
+
+        let mut model = ImplicitFactorizationModel::new(hyper);
+
+        assert!(model.fit(&data, 5).unwrap().is_finite());
+    }
+
+    #[test]
+    fn it_works_with_momentum() {
+        let data = load_movielens("data.csv");
+
+        let hyper = HyperparametersBuilder::default()
+            .learning_rate(0.1)
+            .latent_dim(32)
+            .optimizer(Optimizer::Momentum { decay: 0.9 })
+            .build()
+            .unwrap();
+
+        let mut model = ImplicitFactorizationModel::new(hyper);
+
+        assert!(model.fit(&data, 5).unwrap().is_finite());
+    }
+
+    #[test]
+    fn it_works_with_pointwise_logistic_and_weights() {
+        let data: Vec<WeightedInteraction> = load_movielens("data.csv")
+            .iter()
+            .enumerate()
+            .map(|(i, x)| {
+                let weight = if i % 2 == 0 { 2.0 } else { 0.5 };
+                WeightedInteraction::new(x.user_id(), x.item_id(), weight)
+            })
+            .collect();
+
+        let hyper = HyperparametersBuilder::default()
+            .learning_rate(0.1)
+            .latent_dim(32)
+            .loss(Loss::PointwiseLogistic)
+            .build()
+            .unwrap();
+
+        let mut model = ImplicitFactorizationModel::new(hyper);
+
+        assert!(model.fit(&data, 5).unwrap().is_finite());
+    }
+
+    #[test]
+    fn fm_works() {
+        let data = load_movielens("data.csv");
+        let num_users = data.iter().map(|x| x.user_id()).max().unwrap() + 1;
+
+        // One-hot encode user id and item id into a single sparse feature
+        // vector, so that the factorization machine degenerates to plain
+        // matrix factorization over the same data used by `it_works`.
+        let data: Vec<SparseInteraction> = data.iter()
+            .map(|x| {
+                SparseInteraction::new(
+                    vec![x.user_id(), num_users + x.item_id()],
+                    vec![1.0, 1.0],
+                )
+            })
+            .collect();
+
+        let hyper = FMHyperparametersBuilder::default()
+            .learning_rate(0.1)
+            .latent_dim(32)
+            .num_features_per_example(2)
+            .build()
+            .unwrap();
+
+        let num_epochs = 50;
+
+        let mut model = FactorizationMachineModel::new(hyper);
+        println!("Loss: {}", model.fit(&data, num_epochs).unwrap());
+
+        let prediction = model
+            .predict(data[0].indices(), data[0].values())
+            .unwrap();
+
+        assert!(prediction.is_finite());
+    }
+
+    #[test]
+    fn recurrent_works() {
+        let data = load_movielens("data.csv");
+        let num_users = data.iter().map(|x| x.user_id()).max().unwrap() + 1;
+
+        let mut sequences: Vec<UserSequence> = vec![Vec::new(); num_users];
+        for datum in &data {
+            sequences[datum.user_id()].push(datum.item_id());
+        }
+        sequences.retain(|sequence| !sequence.is_empty());
+
+        let hyper = RecurrentHyperparametersBuilder::default()
+            .learning_rate(0.1)
+            .latent_dim(32)
+            .max_sequence_length(4)
+            .build()
+            .unwrap();
+
+        let num_epochs = 5;
+
+        let mut model = RecurrentRecommenderModel::new(hyper);
+        println!("Loss: {}", model.fit(&sequences, num_epochs).unwrap());
+
+        let predictions = model.predict(&sequences[0]).unwrap();
+
+        assert_eq!(predictions.len(), model.num_items().unwrap());
+        assert!(predictions.iter().all(|x| x.is_finite()));
+    }
+
     #[bench]
     fn bench_movielens(b: &mut Bencher) {
         let data = load_movielens("data.csv");